@@ -1,3 +1,7 @@
+use std::collections::HashSet;
+use std::mem;
+use std::ops::Range;
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Token {
     Illegal,
@@ -6,6 +10,9 @@ pub enum Token {
     // identifiers + literals
     Ident(String),
     Int(i32),
+    Float(f64),
+    Str(String),
+    Template(Vec<TemplatePart>),
 
     // operators
     Assign,
@@ -27,7 +34,10 @@ pub enum Token {
     Lbrace,
     Rbrace,
     Comma,
+    Colon,
     Semicolon,
+    Newline,
+    At,
 
     // keywords
     Function,
@@ -37,27 +47,217 @@ pub enum Token {
     If,
     Else,
     Return,
+
+    // user-supplied via Lexer::with_keywords
+    Keyword(String),
+}
+
+/// Binding power of infix operators, lowest first.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Precedence {
+    Lowest,
+    Equals,
+    LessGreater,
+    Sum,
+    Product,
+    Prefix,
+    Call,
+}
+
+impl Token {
+    pub fn is_operator(&self) -> bool {
+        matches!(
+            self,
+            Token::Assign
+                | Token::Plus
+                | Token::Minus
+                | Token::Gt
+                | Token::Lt
+                | Token::Bang
+                | Token::Asterisk
+                | Token::Slash
+                | Token::Eq
+                | Token::Neq
+                | Token::Geq
+                | Token::Leq
+        )
+    }
+
+    pub fn is_keyword(&self) -> bool {
+        matches!(
+            self,
+            Token::Function
+                | Token::Let
+                | Token::True
+                | Token::False
+                | Token::If
+                | Token::Else
+                | Token::Return
+                | Token::Keyword(_)
+        )
+    }
+
+    /// `true` and `false` are both keywords and literals.
+    pub fn is_literal(&self) -> bool {
+        matches!(
+            self,
+            Token::Int(_)
+                | Token::Float(_)
+                | Token::Str(_)
+                | Token::Template(_)
+                | Token::True
+                | Token::False
+        )
+    }
+
+    /// Precedence of the token in infix position; `Lowest` if it is not an infix operator.
+    pub fn precedence(&self) -> Precedence {
+        match self {
+            Token::Eq | Token::Neq => Precedence::Equals,
+            Token::Lt | Token::Gt | Token::Leq | Token::Geq => Precedence::LessGreater,
+            Token::Plus | Token::Minus => Precedence::Sum,
+            Token::Asterisk | Token::Slash => Precedence::Product,
+            Token::Lparen => Precedence::Call,
+            _ => Precedence::Lowest,
+        }
+    }
+}
+
+/// A piece of a `` `...${expr}...` `` template literal.
+#[derive(Debug, PartialEq, Clone)]
+pub enum TemplatePart {
+    Str(String),
+    Expr(Vec<Token>),
+}
+
+pub const KEYWORDS: &[&str] = &["fn", "let", "true", "false", "if", "else", "return"];
+
+/// Nesting templates deeper than this inside interpolations makes the outermost one
+/// `Token::Illegal`, since each level recurses.
+const MAX_TEMPLATE_DEPTH: usize = 64;
+
+/// A position to resume lexing from, taken with `Lexer::state`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct LexerState {
+    position: usize,
+    line: usize,
+    column: usize,
+}
+
+impl LexerState {
+    /// Line of the next unread character, starting at 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Column of the next unread character in chars, starting at 1.
+    pub fn column(&self) -> usize {
+        self.column
+    }
 }
 
 pub struct Lexer {
     input: String,
+    // byte offset and value of each char, so positions index in constant time
+    chars: Vec<(usize, char)>,
     position: usize,
     read_position: usize,
     ch: char,
+    line: usize,
+    column: usize,
+    keywords: HashSet<String>,
+    newlines: bool,
+    template_depth: usize,
+    template_overflow: bool,
 }
 
 impl Lexer {
     pub fn new(input: String) -> Lexer {
+        Lexer::with_keywords(input, HashSet::new())
+    }
+
+    /// Lexes the given words as `Token::Keyword`. Built-in keywords take precedence.
+    pub fn with_keywords(input: String, keywords: HashSet<String>) -> Lexer {
         let mut lexer = Lexer {
+            chars: input.char_indices().collect(),
             input,
             position: 0,
             read_position: 0,
             ch: '\0',
+            line: 1,
+            column: 0,
+            keywords,
+            newlines: false,
+            template_depth: 0,
+            template_overflow: false,
         };
         lexer.read_char();
         lexer
     }
 
+    /// Emits `Token::Newline` for line breaks instead of skipping them.
+    pub fn emit_newlines(mut self, newlines: bool) -> Lexer {
+        self.newlines = newlines;
+        self
+    }
+
+    /// Continues lexing `input` from `state` with this lexer's keywords and newline
+    /// setting. The character at the saved position is re-read, so the input may have
+    /// been edited past that point.
+    pub fn resume(&self, input: String, state: LexerState) -> Lexer {
+        let mut lexer =
+            Lexer::with_keywords(input, self.keywords.clone()).emit_newlines(self.newlines);
+        lexer.read_position = state.position;
+        lexer.read_char();
+        lexer.line = state.line;
+        lexer.column = state.column;
+        lexer
+    }
+
+    pub fn state(&self) -> LexerState {
+        LexerState {
+            position: self.position,
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    /// The part of the input not yet consumed by the lexer.
+    pub fn remaining(&self) -> &str {
+        &self.input[self.byte_offset(self.position)..]
+    }
+
+    /// Like `next`, but also returns the byte range of the input the token was lexed
+    /// from. Pass it to `lexeme` for the source text.
+    pub fn next_with_span(&mut self) -> Option<(Token, Range<usize>)> {
+        self.skip_whitespace();
+        let start = self.byte_offset(self.position);
+        match self.next_token() {
+            Token::Eof => None,
+            token => Some((token, start..self.byte_offset(self.position))),
+        }
+    }
+
+    /// The source text of a span returned by `next_with_span`.
+    pub fn lexeme(&self, span: Range<usize>) -> &str {
+        &self.input[span]
+    }
+
+    fn byte_offset(&self, position: usize) -> usize {
+        self.chars
+            .get(position)
+            .map_or(self.input.len(), |&(offset, _)| offset)
+    }
+
+    /// Whether the lexer has read past the input; a NUL in the input is not the end.
+    fn at_eof(&self) -> bool {
+        self.position >= self.chars.len()
+    }
+
+    fn char_at(&self, position: usize) -> char {
+        self.chars.get(position).map_or('\0', |&(_, ch)| ch)
+    }
+
     fn next_token(&mut self) -> Token {
         self.skip_whitespace();
 
@@ -75,8 +275,13 @@ impl Lexer {
             '{' => Token::Lbrace,
             '}' => Token::Rbrace,
             ',' => Token::Comma,
+            ':' => Token::Colon,
             ';' => Token::Semicolon,
-            '0'..='9' => Token::Int(self.read_int()),
+            '@' => Token::At,
+            '\n' => Token::Newline,
+            '"' => self.read_string(),
+            '`' => self.read_template(),
+            '0'..='9' => self.read_number(),
             'a'..='z' | 'A'..='Z' | '_' => {
                 let ident = self.read_ident();
                 match ident.as_str() {
@@ -87,10 +292,11 @@ impl Lexer {
                     "if" => Token::If,
                     "else" => Token::Else,
                     "return" => Token::Return,
+                    _ if self.keywords.contains(&ident) => Token::Keyword(ident),
                     _ => Token::Ident(ident),
                 }
             }
-            '\0' => Token::Eof,
+            '\0' if self.at_eof() => Token::Eof,
             _ => Token::Illegal,
         };
 
@@ -108,45 +314,239 @@ impl Lexer {
     }
 
     fn skip_whitespace(&mut self) {
-        while let ' ' | '\t' | '\n' | '\r' = self.ch {
-            self.read_char();
+        loop {
+            match self.ch {
+                ' ' | '\t' | '\r' => self.read_char(),
+                '\n' if !self.newlines => self.read_char(),
+                // a trailing `\` joins the line with the next one
+                '\\' if matches!(self.peak_char(), '\n' | '\r') => {
+                    self.read_char();
+                    if self.ch == '\r' {
+                        self.read_char();
+                    }
+                    if self.ch == '\n' {
+                        self.read_char();
+                    }
+                }
+                _ => break,
+            }
         }
     }
 
     fn peak_char(&self) -> char {
-        if self.read_position >= self.input.len() {
-            '\0'
-        } else {
-            self.input.chars().nth(self.read_position).unwrap()
-        }
+        self.char_at(self.read_position)
+    }
+
+    fn peak_second_char(&self) -> char {
+        self.char_at(self.read_position + 1)
     }
 
     fn read_char(&mut self) {
-        if self.read_position >= self.input.len() {
-            self.ch = '\0';
+        if self.ch == '\n' {
+            self.line += 1;
+            self.column = 1;
         } else {
-            self.ch = self.input.chars().nth(self.read_position).unwrap();
+            self.column += 1;
         }
+        self.ch = self.char_at(self.read_position);
         self.position = self.read_position;
         self.read_position += 1;
     }
 
     fn read_ident(&mut self) -> String {
-        let start = self.position;
+        let mut ident = String::from(self.ch);
         while let 'a'..='z' | 'A'..='Z' | '_' = self.peak_char() {
             self.read_char();
+            ident.push(self.ch);
+        }
+        ident
+    }
+
+    fn read_string(&mut self) -> Token {
+        let mut string = String::new();
+        let mut valid = true;
+        loop {
+            self.read_char();
+            match self.ch {
+                '"' => break,
+                '\0' if self.at_eof() => return Token::Illegal,
+                '\\' => {
+                    self.read_char();
+                    match self.read_escape() {
+                        Some(ch) => string.push(ch),
+                        None => valid = false,
+                    }
+                }
+                ch => string.push(ch),
+            }
+        }
+        if valid {
+            Token::Str(string)
+        } else {
+            Token::Illegal
         }
-        String::from(&self.input[start..=self.position])
     }
 
-    fn read_int(&mut self) -> i32 {
-        let start = self.position;
-        while let '0'..='9' = self.peak_char() {
+    fn read_template(&mut self) -> Token {
+        if self.template_depth == MAX_TEMPLATE_DEPTH {
+            self.template_overflow = true;
+            return Token::Illegal;
+        }
+        self.template_depth += 1;
+        let token = self.read_template_parts();
+        self.template_depth -= 1;
+        if self.template_overflow {
+            self.template_overflow = self.template_depth > 0;
+            return Token::Illegal;
+        }
+        token
+    }
+
+    fn read_template_parts(&mut self) -> Token {
+        let mut parts = Vec::new();
+        let mut string = String::new();
+        let mut valid = true;
+        self.read_char();
+        loop {
+            match self.ch {
+                '`' => break,
+                '\0' if self.at_eof() => return Token::Illegal,
+                '$' if self.peak_char() == '{' => {
+                    self.read_char();
+                    self.read_char();
+                    let Some(tokens) = self.read_interpolation() else {
+                        return Token::Illegal;
+                    };
+                    if tokens.is_empty() {
+                        valid = false;
+                    }
+                    if !string.is_empty() {
+                        parts.push(TemplatePart::Str(mem::take(&mut string)));
+                    }
+                    parts.push(TemplatePart::Expr(tokens));
+                    continue;
+                }
+                '\\' => {
+                    self.read_char();
+                    match self.ch {
+                        '`' | '$' => string.push(self.ch),
+                        _ => match self.read_escape() {
+                            Some(ch) => string.push(ch),
+                            None => valid = false,
+                        },
+                    }
+                }
+                ch => string.push(ch),
+            }
             self.read_char();
         }
-        String::from(&self.input[start..=self.position])
-            .parse()
-            .unwrap()
+        if !valid {
+            return Token::Illegal;
+        }
+        if !string.is_empty() {
+            parts.push(TemplatePart::Str(string));
+        }
+        Token::Template(parts)
+    }
+
+    /// Lexes an interpolated expression up to the `}` that closes it, leaving the lexer
+    /// on the character after it. `None` if the input ends first.
+    fn read_interpolation(&mut self) -> Option<Vec<Token>> {
+        let mut tokens = Vec::new();
+        let mut depth = 0;
+        loop {
+            let token = self.next_token();
+            match token {
+                Token::Eof => return None,
+                Token::Rbrace if depth == 0 => return Some(tokens),
+                Token::Lbrace => depth += 1,
+                Token::Rbrace => depth -= 1,
+                _ => {}
+            }
+            tokens.push(token);
+        }
+    }
+
+    /// Decodes the escape starting at the current character (just after the `\`).
+    fn read_escape(&mut self) -> Option<char> {
+        match self.ch {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '0' => Some('\0'),
+            '\\' => Some('\\'),
+            '"' => Some('"'),
+            'x' => {
+                let mut code = 0;
+                for _ in 0..2 {
+                    let digit = self.peak_char().to_digit(16)?;
+                    self.read_char();
+                    code = code * 16 + digit;
+                }
+                // as in Rust, `\x` only escapes ASCII
+                char::from_u32(code).filter(char::is_ascii)
+            }
+            'u' => {
+                if self.peak_char() != '{' {
+                    return None;
+                }
+                self.read_char();
+                let mut code: u32 = 0;
+                let mut digits = 0;
+                while let Some(digit) = self.peak_char().to_digit(16) {
+                    self.read_char();
+                    code = code.saturating_mul(16).saturating_add(digit);
+                    digits += 1;
+                }
+                if self.peak_char() != '}' || !(1..=6).contains(&digits) {
+                    return None;
+                }
+                self.read_char();
+                char::from_u32(code)
+            }
+            _ => None,
+        }
+    }
+
+    fn read_number(&mut self) -> Token {
+        let mut number = String::from(self.ch);
+        loop {
+            match self.peak_char() {
+                '0'..='9' | 'a'..='z' | 'A'..='Z' | '_' => {}
+                '.' if !number.contains('.')
+                    && matches!(self.peak_second_char(), '0'..='9' | '_') => {}
+                _ => break,
+            }
+            self.read_char();
+            number.push(self.ch);
+        }
+        Lexer::parse_number(&number).unwrap_or(Token::Illegal)
+    }
+
+    /// Parses digits with single `_` separators between them, an optional fraction and an
+    /// optional `i` or `f` suffix.
+    fn parse_number(number: &str) -> Option<Token> {
+        let digits = |part: &str| {
+            part.split('_')
+                .all(|group| !group.is_empty() && group.chars().all(|ch| ch.is_ascii_digit()))
+                .then(|| part.replace('_', ""))
+        };
+
+        let body = number.trim_end_matches(|ch: char| ch.is_ascii_alphabetic());
+        let suffix = &number[body.len()..];
+        let (int, frac) = match body.split_once('.') {
+            Some((int, frac)) => (digits(int)?, Some(digits(frac)?)),
+            None => (digits(body)?, None),
+        };
+
+        let float = match (frac, suffix) {
+            (None, "" | "i") => return int.parse().ok().map(Token::Int),
+            (None, "f") => int.parse(),
+            (Some(frac), "" | "f") => format!("{int}.{frac}").parse(),
+            _ => return None,
+        };
+        // like oversized integers, floats too big for f64 are illegal
+        float.ok().filter(|f: &f64| f.is_finite()).map(Token::Float)
     }
 }
 
@@ -163,9 +563,10 @@ impl Iterator for Lexer {
 
 #[cfg(test)]
 mod test {
-    use std::iter::zip;
+    use std::collections::HashSet;
+    use std::iter::{self, zip};
 
-    use super::{Lexer, Token};
+    use super::{Lexer, Precedence, TemplatePart, Token, KEYWORDS};
 
     #[test]
     fn single_next_token() {
@@ -198,7 +599,7 @@ mod test {
     #[test]
     fn double_next_token() {
         let input = String::from("== != >= <=");
-        let lexer = Lexer::new(input.into());
+        let lexer = Lexer::new(input);
         let tokens = vec![Token::Eq, Token::Neq, Token::Geq, Token::Leq];
 
         for (expected, actual) in zip(tokens, lexer) {
@@ -207,6 +608,427 @@ mod test {
         }
     }
 
+    #[test]
+    fn suffix_next_token() {
+        let input = String::from("5f 5i 5 5z");
+        let lexer = Lexer::new(input);
+        let tokens = vec![
+            Token::Float(5.0),
+            Token::Int(5),
+            Token::Int(5),
+            Token::Illegal,
+        ];
+
+        for (expected, actual) in zip(tokens, lexer) {
+            println!("expected: {:?} recieved: {:?}", expected, actual);
+            assert_eq!(expected, actual);
+        }
+    }
+
+    #[test]
+    fn custom_keyword_next_token() {
+        let input = String::from("repeat repeats let");
+        let keywords = HashSet::from([String::from("repeat"), String::from("let")]);
+        let lexer = Lexer::with_keywords(input, keywords);
+        let tokens = vec![
+            Token::Keyword(String::from("repeat")),
+            Token::Ident(String::from("repeats")),
+            Token::Let,
+        ];
+
+        for (expected, actual) in zip(tokens, lexer) {
+            println!("expected: {:?} recieved: {:?}", expected, actual);
+            assert_eq!(expected, actual);
+        }
+    }
+
+    #[test]
+    fn colon_next_token() {
+        let input = String::from("switch (x) { case 1: y; default: z; }");
+        let lexer = Lexer::new(input);
+        let tokens = vec![
+            Token::Ident(String::from("switch")),
+            Token::Lparen,
+            Token::Ident(String::from("x")),
+            Token::Rparen,
+            Token::Lbrace,
+            Token::Ident(String::from("case")),
+            Token::Int(1),
+            Token::Colon,
+            Token::Ident(String::from("y")),
+            Token::Semicolon,
+            Token::Ident(String::from("default")),
+            Token::Colon,
+            Token::Ident(String::from("z")),
+            Token::Semicolon,
+            Token::Rbrace,
+        ];
+
+        assert_eq!(tokens, lexer.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn float_next_token() {
+        let input = String::from("1_000 1_000.5 1_000.500_1 2.5f 0.25;");
+        let lexer = Lexer::new(input);
+        let tokens = vec![
+            Token::Int(1000),
+            Token::Float(1000.5),
+            Token::Float(1000.5001),
+            Token::Float(2.5),
+            Token::Float(0.25),
+            Token::Semicolon,
+        ];
+
+        assert_eq!(tokens, lexer.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn illegal_number_next_token() {
+        let huge = "9".repeat(400);
+        let input = format!("1_.5 1._5 1.5_ 1__0 1.5i 99999999999 {huge}f {huge}.5 x");
+        let lexer = Lexer::new(input);
+        let tokens = vec![
+            Token::Illegal,
+            Token::Illegal,
+            Token::Illegal,
+            Token::Illegal,
+            Token::Illegal,
+            Token::Illegal,
+            Token::Illegal,
+            Token::Illegal,
+            Token::Ident(String::from("x")),
+        ];
+
+        assert_eq!(tokens, lexer.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn string_next_token() {
+        let input = String::from(r#""foo" "a\tb\n" "\"q\" \\" "é" x"#);
+        let lexer = Lexer::new(input);
+        let tokens = vec![
+            Token::Str(String::from("foo")),
+            Token::Str(String::from("a\tb\n")),
+            Token::Str(String::from("\"q\" \\")),
+            Token::Str(String::from("é")),
+            Token::Ident(String::from("x")),
+        ];
+
+        for (expected, actual) in zip(tokens, lexer) {
+            println!("expected: {:?} recieved: {:?}", expected, actual);
+            assert_eq!(expected, actual);
+        }
+    }
+
+    #[test]
+    fn string_escape_next_token() {
+        let input = String::from(r#""\x41" "\u{1F600}" "\x1" "\u{110000}" "\u{zz}" "\q" "open"#);
+        let lexer = Lexer::new(input);
+        let tokens = vec![
+            Token::Str(String::from("A")),
+            Token::Str(String::from("\u{1F600}")),
+            Token::Illegal,
+            Token::Illegal,
+            Token::Illegal,
+            Token::Illegal,
+            Token::Illegal,
+        ];
+
+        assert_eq!(tokens, lexer.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn string_byte_escape_next_token() {
+        let input = String::from(r#""\x7f" "\x80" "\xff" x"#);
+        let lexer = Lexer::new(input);
+        let tokens = vec![
+            Token::Str(String::from("\x7f")),
+            Token::Illegal,
+            Token::Illegal,
+            Token::Ident(String::from("x")),
+        ];
+
+        assert_eq!(tokens, lexer.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn nul_next_token() {
+        let input = String::from("\"a\0b\" x \0 y");
+        let lexer = Lexer::new(input);
+        let tokens = vec![
+            Token::Str(String::from("a\0b")),
+            Token::Ident(String::from("x")),
+            Token::Illegal,
+            Token::Ident(String::from("y")),
+        ];
+
+        assert_eq!(tokens, lexer.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn line_continuation_next_token() {
+        let input = String::from("1 + \\\n2 \\\r\n* 3 \\ 4");
+        let lexer = Lexer::new(input);
+        let tokens = vec![
+            Token::Int(1),
+            Token::Plus,
+            Token::Int(2),
+            Token::Asterisk,
+            Token::Int(3),
+            Token::Illegal,
+            Token::Int(4),
+        ];
+
+        assert_eq!(tokens, lexer.collect::<Vec<_>>());
+
+        let mut lexer = Lexer::new(String::from("1 + \\\n2 \\\r\n* 3"));
+        lexer.by_ref().take(3).for_each(drop);
+        assert_eq!(2, lexer.state().line());
+        lexer.next();
+        assert_eq!(3, lexer.state().line());
+    }
+
+    #[test]
+    fn resume_from_state() {
+        let input = String::from("let add = fn(x, y) { x + y; };");
+        let expected: Vec<Token> = Lexer::new(input.clone()).skip(4).collect();
+
+        let mut lexer = Lexer::new(input.clone());
+        lexer.by_ref().take(4).for_each(drop);
+        let resumed = lexer.resume(input, lexer.state());
+
+        assert_eq!(expected, resumed.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn resume_from_state_edited_input() {
+        let mut lexer = Lexer::new(String::from("let x = 5;"));
+        lexer.by_ref().take(3).for_each(drop);
+        let resumed = lexer.resume(String::from("let x = y * 2;"), lexer.state());
+        let tokens = vec![
+            Token::Ident(String::from("y")),
+            Token::Asterisk,
+            Token::Int(2),
+            Token::Semicolon,
+        ];
+
+        assert_eq!(tokens, resumed.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn resume_keeps_config() {
+        let input = String::from("repeat\nx");
+        let keywords = HashSet::from([String::from("repeat")]);
+        let mut lexer = Lexer::with_keywords(input, keywords).emit_newlines(true);
+        lexer.next();
+        let resumed = lexer.resume(String::from("repeat\nrepeat"), lexer.state());
+        let tokens = vec![Token::Newline, Token::Keyword(String::from("repeat"))];
+
+        assert_eq!(tokens, resumed.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn state_line_column() {
+        let mut lexer = Lexer::new(String::from("let é\n  = 5;"));
+        assert_eq!((1, 1), (lexer.state().line(), lexer.state().column()));
+
+        lexer.by_ref().take(2).for_each(drop);
+        assert_eq!((1, 6), (lexer.state().line(), lexer.state().column()));
+
+        lexer.next();
+        let state = lexer.state();
+        assert_eq!((2, 4), (state.line(), state.column()));
+
+        let resumed = lexer.resume(String::from("let é\n  = 5;"), state);
+        assert_eq!(state, resumed.state());
+    }
+
+    #[test]
+    fn keywords_next_token() {
+        for keyword in KEYWORDS {
+            let token = Lexer::new(keyword.to_string()).next().unwrap();
+            assert!(
+                !matches!(token, Token::Ident(_)),
+                "{keyword} lexed as {token:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn template_next_token() {
+        let input = String::from("`hello ${name}, you have ${count + 1} items` `\\${x}` ``");
+        let lexer = Lexer::new(input);
+        let tokens = vec![
+            Token::Template(vec![
+                TemplatePart::Str(String::from("hello ")),
+                TemplatePart::Expr(vec![Token::Ident(String::from("name"))]),
+                TemplatePart::Str(String::from(", you have ")),
+                TemplatePart::Expr(vec![
+                    Token::Ident(String::from("count")),
+                    Token::Plus,
+                    Token::Int(1),
+                ]),
+                TemplatePart::Str(String::from(" items")),
+            ]),
+            Token::Template(vec![TemplatePart::Str(String::from("${x}"))]),
+            Token::Template(vec![]),
+        ];
+
+        assert_eq!(tokens, lexer.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn unterminated_template_next_token() {
+        let unterminated = Lexer::new(String::from("`a ${ fn() { x }"));
+        assert_eq!(vec![Token::Illegal], unterminated.collect::<Vec<_>>());
+
+        let unclosed = Lexer::new(String::from("`a ${x` + 1"));
+        assert_eq!(vec![Token::Illegal], unclosed.collect::<Vec<_>>());
+
+        let empty = Lexer::new(String::from("`a ${} b` x"));
+        let tokens = vec![Token::Illegal, Token::Ident(String::from("x"))];
+        assert_eq!(tokens, empty.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn nested_literal_template_next_token() {
+        let input = String::from("`a ${ \"}\" } b` `${ `x` }`");
+        let lexer = Lexer::new(input);
+        let tokens = vec![
+            Token::Template(vec![
+                TemplatePart::Str(String::from("a ")),
+                TemplatePart::Expr(vec![Token::Str(String::from("}"))]),
+                TemplatePart::Str(String::from(" b")),
+            ]),
+            Token::Template(vec![TemplatePart::Expr(vec![Token::Template(vec![
+                TemplatePart::Str(String::from("x")),
+            ])])]),
+        ];
+
+        assert_eq!(tokens, lexer.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn newline_next_token() {
+        let lexer = Lexer::new(String::from("a\nb"));
+        let tokens = vec![
+            Token::Ident(String::from("a")),
+            Token::Ident(String::from("b")),
+        ];
+        assert_eq!(tokens, lexer.collect::<Vec<_>>());
+
+        let lexer = Lexer::new(String::from("a\nb\r\nc \\\nd")).emit_newlines(true);
+        let tokens = vec![
+            Token::Ident(String::from("a")),
+            Token::Newline,
+            Token::Ident(String::from("b")),
+            Token::Newline,
+            Token::Ident(String::from("c")),
+            Token::Ident(String::from("d")),
+        ];
+        assert_eq!(tokens, lexer.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn token_kind() {
+        assert!(Token::Plus.is_operator());
+        assert!(Token::Leq.is_operator());
+        assert!(!Token::Plus.is_keyword());
+        assert!(Token::Let.is_keyword());
+        assert!(Token::Keyword(String::from("repeat")).is_keyword());
+        assert!(!Token::Ident(String::from("x")).is_keyword());
+        assert!(Token::Int(5).is_literal());
+        assert!(Token::Str(String::from("a")).is_literal());
+        assert!(Token::True.is_literal() && Token::True.is_keyword());
+        assert!(!Token::Lparen.is_operator() && !Token::Lparen.is_literal());
+    }
+
+    #[test]
+    fn token_precedence() {
+        assert_eq!(Precedence::Equals, Token::Neq.precedence());
+        assert_eq!(Precedence::LessGreater, Token::Geq.precedence());
+        assert_eq!(Precedence::Sum, Token::Plus.precedence());
+        assert_eq!(Precedence::Product, Token::Slash.precedence());
+        assert_eq!(Precedence::Call, Token::Lparen.precedence());
+        assert_eq!(Precedence::Lowest, Token::Let.precedence());
+        assert_eq!(Precedence::Lowest, Token::Bang.precedence());
+        assert!(Token::Asterisk.precedence() > Token::Plus.precedence());
+    }
+
+    #[test]
+    fn span_next_token() {
+        let input = String::from("let x==10 != \"a\\tb\" fn 1_0f é");
+        let mut lexer = Lexer::new(input);
+        let spans: Vec<_> = iter::from_fn(|| lexer.next_with_span()).collect();
+        let lexemes = vec![
+            (Token::Let, "let"),
+            (Token::Ident(String::from("x")), "x"),
+            (Token::Eq, "=="),
+            (Token::Int(10), "10"),
+            (Token::Neq, "!="),
+            (Token::Str(String::from("a\tb")), "\"a\\tb\""),
+            (Token::Function, "fn"),
+            (Token::Float(10.0), "1_0f"),
+            (Token::Illegal, "é"),
+        ];
+
+        let actual: Vec<_> = spans
+            .into_iter()
+            .map(|(token, span)| (token, lexer.lexeme(span)))
+            .collect();
+        assert_eq!(lexemes, actual);
+        assert_eq!(None, lexer.next_with_span());
+    }
+
+    #[test]
+    fn attribute_next_token() {
+        let input = String::from("@memoize fn(n) {} @ pure@@let \"@x\"");
+        let lexer = Lexer::new(input);
+        let tokens = vec![
+            Token::At,
+            Token::Ident(String::from("memoize")),
+            Token::Function,
+            Token::Lparen,
+            Token::Ident(String::from("n")),
+            Token::Rparen,
+            Token::Lbrace,
+            Token::Rbrace,
+            Token::At,
+            Token::Ident(String::from("pure")),
+            Token::At,
+            Token::At,
+            Token::Let,
+            Token::Str(String::from("@x")),
+        ];
+
+        assert_eq!(tokens, lexer.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn remaining_input() {
+        let input = String::from("let x = 5; y");
+        let mut lexer = Lexer::new(input);
+        assert_eq!("let x = 5; y", lexer.remaining());
+
+        lexer.by_ref().take(3).for_each(drop);
+        assert_eq!(" 5; y", lexer.remaining());
+
+        lexer.by_ref().for_each(drop);
+        assert_eq!("", lexer.remaining());
+    }
+
+    #[test]
+    fn remaining_multibyte_input() {
+        let input = String::from("é → let");
+        let mut lexer = Lexer::new(input);
+
+        assert_eq!(Some(Token::Illegal), lexer.next());
+        assert_eq!(" → let", lexer.remaining());
+        assert_eq!(Some(Token::Illegal), lexer.next());
+        assert_eq!(" let", lexer.remaining());
+    }
+
     #[test]
     fn simple_next_token() {
         let input = String::from(
@@ -226,7 +1048,7 @@ mod test {
             let result = add(five, ten);
         ",
         );
-        let lexer = Lexer::new(input.into());
+        let lexer = Lexer::new(input);
 
         let tokens = vec![
             Token::Let,
@@ -303,3 +1125,98 @@ mod test {
         }
     }
 }
+
+#[cfg(test)]
+mod adversarial_test {
+    use std::time::{Duration, Instant};
+
+    use super::{Lexer, Token};
+
+    const TIME_LIMIT: Duration = Duration::from_secs(5);
+    const MEGABYTE: usize = 1 << 20;
+
+    fn lex(input: String) -> Vec<Token> {
+        let start = Instant::now();
+        let tokens: Vec<Token> = Lexer::new(input).collect();
+        let elapsed = start.elapsed();
+        assert!(elapsed < TIME_LIMIT, "lexing took {:?}", elapsed);
+        tokens
+    }
+
+    #[test]
+    fn run_of_assign() {
+        let tokens = lex("=".repeat(MEGABYTE + 1));
+        assert_eq!(MEGABYTE / 2 + 1, tokens.len());
+        assert!(tokens[..MEGABYTE / 2]
+            .iter()
+            .all(|token| *token == Token::Eq));
+        assert_eq!(Some(&Token::Assign), tokens.last());
+    }
+
+    #[test]
+    fn deeply_nested_parens() {
+        let depth = 100_000;
+        let tokens = lex(format!("{}1{}", "(".repeat(depth), ")".repeat(depth)));
+        assert_eq!(2 * depth + 1, tokens.len());
+        assert!(tokens[..depth].iter().all(|token| *token == Token::Lparen));
+        assert_eq!(Token::Int(1), tokens[depth]);
+        assert!(tokens[depth + 1..]
+            .iter()
+            .all(|token| *token == Token::Rparen));
+    }
+
+    #[test]
+    fn gigantic_number() {
+        assert_eq!(vec![Token::Illegal], lex("9".repeat(MEGABYTE)));
+        assert_eq!(vec![Token::Illegal], lex("1_".repeat(MEGABYTE / 2)));
+
+        let float = lex(format!("0.{}", "0".repeat(MEGABYTE)));
+        assert_eq!(vec![Token::Float(0.0)], float);
+    }
+
+    #[test]
+    fn many_escapes() {
+        let count = 10_000;
+        let tokens = lex(format!("\"{}\"", r"\n\x41\u{1F600}\\".repeat(count)));
+        assert_eq!(vec![Token::Str("\nA\u{1F600}\\".repeat(count))], tokens);
+    }
+
+    #[test]
+    fn unterminated_literals() {
+        assert_eq!(
+            vec![Token::Illegal],
+            lex(format!("\"{}", "a".repeat(MEGABYTE)))
+        );
+        assert_eq!(
+            vec![Token::Illegal],
+            lex(format!("`${{{}", "{".repeat(MEGABYTE)))
+        );
+    }
+
+    #[test]
+    fn nested_templates() {
+        assert_eq!(vec![Token::Illegal], lex("`${".repeat(10_000)));
+
+        let limit = format!("{}x{}", "`${".repeat(64), "}`".repeat(64));
+        assert!(matches!(lex(limit)[..], [Token::Template(_)]));
+
+        let depth = 10_000;
+        let closed = format!("{}x{} y", "`${".repeat(depth), "}`".repeat(depth));
+        assert_eq!(
+            vec![Token::Illegal, Token::Ident(String::from("y"))],
+            lex(closed)
+        );
+    }
+
+    #[test]
+    fn all_whitespace() {
+        assert_eq!(Vec::<Token>::new(), lex(" \t\r\n".repeat(MEGABYTE / 4)));
+    }
+
+    #[test]
+    fn non_ascii() {
+        let tokens = lex("é→🙂".repeat(MEGABYTE / 8));
+        assert_eq!(3 * (MEGABYTE / 8), tokens.len());
+        assert!(tokens.iter().all(|token| *token == Token::Illegal));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod lexer;
+pub mod lint;
+pub mod source_map;
@@ -0,0 +1,58 @@
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Warning {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "warning: line {}: {}", self.line, self.message)
+    }
+}
+
+/// Warns about lines whose indentation mixes tabs and spaces. Line numbers are 1-based.
+pub fn mixed_indentation(input: &str) -> Vec<Warning> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            let indent = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+            indent.contains(' ') && indent.contains('\t')
+        })
+        .map(|(i, _)| Warning {
+            line: i + 1,
+            message: String::from("indentation mixes tabs and spaces"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{mixed_indentation, Warning};
+
+    #[test]
+    fn mixed_indentation_warns() {
+        let input = "let f = fn(x) {\n\t x;\n  \ty;\n};\n";
+        let expected = vec![
+            Warning {
+                line: 2,
+                message: String::from("indentation mixes tabs and spaces"),
+            },
+            Warning {
+                line: 3,
+                message: String::from("indentation mixes tabs and spaces"),
+            },
+        ];
+        assert_eq!(expected, mixed_indentation(input));
+    }
+
+    #[test]
+    fn consistent_indentation() {
+        let spaces = "let f = fn(x) {\n    x\t+ 1;\n};\n";
+        let tabs = "let f = fn(x) {\n\t\tx +  1;\n};\n";
+        assert_eq!(Vec::<Warning>::new(), mixed_indentation(spaces));
+        assert_eq!(Vec::<Warning>::new(), mixed_indentation(tabs));
+    }
+}
@@ -1,4 +1,3 @@
-mod lexer;
 mod repl;
 
 fn main() {
@@ -1,27 +1,391 @@
-use crate::lexer::Lexer;
-use std::io::{self, Write};
+use monkey::lexer::Lexer;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
 
 const PROMPT: &str = ">> ";
+const PASTE_END: &str = ".";
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+#[cfg(any(feature = "rustyline", test))]
+const COMMANDS: &[&str] = &["exit", ":clear", ":paste", ":rerun", ":!", ":save"];
 
 pub fn start() {
-    loop {
-        print!("{PROMPT}");
-        io::stdout().flush().unwrap();
+    let tty = io::stdout().is_terminal();
 
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
+    #[cfg(feature = "rustyline")]
+    if let Ok(editor) = editor::Editor::new() {
+        return run(editor, io::stdout(), tty);
+    }
+
+    run(io::stdin().lock(), io::stdout(), tty);
+}
+
+/// REPL output that also keeps a transcript of the session for `:save`.
+struct Recorder<W> {
+    output: W,
+    transcript: Vec<u8>,
+}
+
+impl<W: Write> Recorder<W> {
+    fn new(output: W) -> Recorder<W> {
+        Recorder {
+            output,
+            transcript: Vec::new(),
+        }
+    }
+
+    /// Adds text to the transcript that was shown without going through `output`,
+    /// like a line echoed by the terminal.
+    fn record(&mut self, text: &str) {
+        self.transcript.extend_from_slice(text.as_bytes());
+    }
+}
 
-        let trimmed_input = input.trim();
+impl<W: Write> Write for Recorder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.output.write(buf)?;
+        self.transcript.extend_from_slice(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
+}
+
+/// A source of input lines for the REPL.
+trait Input {
+    /// Shows `prompt` and reads the next line without its line ending, or `None` at EOF.
+    /// The prompt and line end up in the transcript.
+    fn read_line<W: Write>(&mut self, prompt: &str, output: &mut Recorder<W>) -> Option<String>;
+}
+
+impl<R: BufRead> Input for R {
+    fn read_line<W: Write>(&mut self, prompt: &str, output: &mut Recorder<W>) -> Option<String> {
+        write!(output, "{prompt}").unwrap();
+        output.flush().unwrap();
+
+        let mut line = String::new();
+        match BufRead::read_line(self, &mut line).unwrap() {
+            0 => None,
+            _ => {
+                let line = line.trim_end_matches(['\n', '\r']).to_string();
+                output.record(&format!("{line}\n"));
+                Some(line)
+            }
+        }
+    }
+}
 
-        if trimmed_input == "exit" {
+/// REPL commands and keywords starting with `prefix`, for tab completion.
+#[cfg(any(feature = "rustyline", test))]
+fn completions(prefix: &str) -> Vec<&'static str> {
+    let mut candidates: Vec<_> = COMMANDS
+        .iter()
+        .chain(monkey::lexer::KEYWORDS)
+        .copied()
+        .filter(|candidate| candidate.starts_with(prefix))
+        .collect();
+    candidates.sort();
+    candidates
+}
+
+fn run<I: Input, W: Write>(mut input: I, output: W, tty: bool) {
+    let mut output = Recorder::new(output);
+    let mut previous: Option<String> = None;
+
+    while let Some(line) = input.read_line(PROMPT, &mut output) {
+        let trimmed_input = line.trim();
+        let (command, argument) = trimmed_input
+            .split_once(char::is_whitespace)
+            .unwrap_or((trimmed_input, ""));
+
+        match trimmed_input {
+            "exit" => break,
+            ":clear" => {
+                if tty {
+                    write!(output, "{CLEAR_SCREEN}").unwrap();
+                }
+            }
+            ":paste" => {
+                let source = read_paste(&mut input, &mut output).trim().to_string();
+                eval(&mut output, &source);
+                if !source.is_empty() {
+                    previous = Some(source);
+                }
+            }
+            ":rerun" | ":!" => match &previous {
+                Some(source) => eval(&mut output, source),
+                None => writeln!(output, "no previous input to rerun").unwrap(),
+            },
+            _ if command == ":save" => save(&mut output, argument.trim()),
+            _ => {
+                eval(&mut output, trimmed_input);
+                if !trimmed_input.is_empty() {
+                    previous = Some(trimmed_input.to_string());
+                }
+            }
+        }
+    }
+}
+
+/// Collects raw lines until a line containing only `.` or EOF.
+fn read_paste<I: Input, W: Write>(input: &mut I, output: &mut Recorder<W>) -> String {
+    let mut source = String::new();
+    while let Some(line) = input.read_line("", output) {
+        if line.trim_end() == PASTE_END {
             break;
         }
+        source.push_str(&line);
+        source.push('\n');
+    }
+    source
+}
+
+fn eval<W: Write>(output: &mut W, input: &str) {
+    let tokens: String = Lexer::new(input.into())
+        .map(|token| format!("{:?} ", token))
+        .collect();
+
+    writeln!(output, "{tokens}").unwrap();
+}
+
+fn save<W: Write>(output: &mut Recorder<W>, path: &str) {
+    if path.is_empty() {
+        writeln!(output, "usage: :save <path>").unwrap();
+    } else if let Err(err) = fs::write(path, &output.transcript) {
+        writeln!(output, "could not save transcript to {path}: {err}").unwrap();
+    }
+}
+
+#[cfg(feature = "rustyline")]
+mod editor {
+    use super::{completions, Input, Recorder};
+    use rustyline::completion::Completer;
+    use rustyline::error::ReadlineError;
+    use rustyline::highlight::Highlighter;
+    use rustyline::hint::Hinter;
+    use rustyline::history::DefaultHistory;
+    use rustyline::validate::Validator;
+    use rustyline::{Context, Helper};
+    use std::io::Write;
+
+    pub struct Editor(rustyline::Editor<Completions, DefaultHistory>);
+
+    impl Editor {
+        pub fn new() -> rustyline::Result<Editor> {
+            let mut editor = rustyline::Editor::new()?;
+            editor.set_helper(Some(Completions));
+            Ok(Editor(editor))
+        }
+    }
+
+    impl Input for Editor {
+        fn read_line<W: Write>(
+            &mut self,
+            prompt: &str,
+            output: &mut Recorder<W>,
+        ) -> Option<String> {
+            // rustyline writes to the terminal directly, so show anything still buffered
+            output.flush().unwrap();
+            match self.0.readline(prompt) {
+                Ok(line) => {
+                    if !line.trim().is_empty() {
+                        let _ = self.0.add_history_entry(line.as_str());
+                    }
+                    output.record(&format!("{prompt}{line}\n"));
+                    Some(line)
+                }
+                Err(ReadlineError::Interrupted) => {
+                    output.record(&format!("{prompt}\n"));
+                    Some(String::new())
+                }
+                Err(_) => None,
+            }
+        }
+    }
 
-        let lexer = Lexer::new(input.trim().into());
+    pub struct Completions;
 
-        for token in lexer {
-            print!("{:?} ", token)
+    impl Completer for Completions {
+        type Candidate = String;
+
+        fn complete(
+            &self,
+            line: &str,
+            pos: usize,
+            _ctx: &Context<'_>,
+        ) -> rustyline::Result<(usize, Vec<String>)> {
+            let start = line[..pos]
+                .char_indices()
+                .rev()
+                .find(|&(_, ch)| ch.is_whitespace() || "(){},;".contains(ch))
+                .map_or(0, |(i, ch)| i + ch.len_utf8());
+            let candidates = completions(&line[start..pos])
+                .into_iter()
+                .map(String::from)
+                .collect();
+            Ok((start, candidates))
         }
-        println!();
+    }
+
+    impl Hinter for Completions {
+        type Hint = String;
+    }
+
+    impl Highlighter for Completions {}
+
+    impl Validator for Completions {}
+
+    impl Helper for Completions {}
+}
+
+#[cfg(test)]
+mod test {
+    use super::{completions, run};
+    use std::{env, fs};
+
+    fn session(input: &str) -> String {
+        tty_session(input, false)
+    }
+
+    fn tty_session(input: &str, tty: bool) -> String {
+        let mut output = Vec::new();
+        run(input.as_bytes(), &mut output, tty);
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn complete_prefix() {
+        assert_eq!(vec!["else", "exit"], completions("e"));
+        assert_eq!(vec![":paste"], completions(":p"));
+        assert_eq!(Vec::<&str>::new(), completions("xyz"));
+    }
+
+    #[cfg(feature = "rustyline")]
+    #[test]
+    fn complete_word() {
+        use super::editor::Completions;
+        use rustyline::completion::Completer;
+        use rustyline::history::DefaultHistory;
+        use rustyline::Context;
+
+        let history = DefaultHistory::new();
+        let complete = |line: &str| {
+            Completions
+                .complete(line, line.len(), &Context::new(&history))
+                .unwrap()
+        };
+
+        assert_eq!((0, vec![String::from("let")]), complete("le"));
+        assert_eq!((2, vec![String::from("fn")]), complete("x(fn"));
+        assert_eq!((4, vec![String::from("let")]), complete("x\u{3000}le"));
+        assert_eq!((0, Vec::<String>::new()), complete("xyz"));
+    }
+
+    #[test]
+    fn clear_screen() {
+        assert_eq!(">> \x1b[2J\x1b[H>> ", tty_session(":clear\n", true));
+        assert_eq!(">> >> ", tty_session(":clear\n", false));
+    }
+
+    #[test]
+    fn rerun_previous_input() {
+        let output = session("1 + 1\n\n:clear\n:rerun\n:!\n");
+        let expected = concat!(
+            ">> Int(1) Plus Int(1) \n",
+            ">> \n",
+            ">> ",
+            ">> Int(1) Plus Int(1) \n",
+            ">> Int(1) Plus Int(1) \n",
+            ">> ",
+        );
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn rerun_without_previous_input() {
+        let output = session(":rerun\n");
+        assert_eq!(">> no previous input to rerun\n>> ", output);
+    }
+
+    #[test]
+    fn rerun_after_empty_paste() {
+        let output = session(":paste\n.\n:rerun\n");
+        assert_eq!(">> \n>> no previous input to rerun\n>> ", output);
+    }
+
+    #[test]
+    fn save_transcript() {
+        let path = env::temp_dir().join(format!("monkey-transcript-{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        session(&format!("let a = 1;\na;\n:save {path}\n"));
+
+        let transcript = fs::read_to_string(path).unwrap();
+        fs::remove_file(path).unwrap();
+        let expected = concat!(
+            ">> let a = 1;\n",
+            "Let Ident(\"a\") Assign Int(1) Semicolon \n",
+            ">> a;\n",
+            "Ident(\"a\") Semicolon \n",
+            ">> :save ",
+        );
+        assert_eq!(format!("{expected}{path}\n"), transcript);
+    }
+
+    #[test]
+    fn save_transcript_commands() {
+        let path = env::temp_dir().join(format!("monkey-commands-{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        session(&format!(":rerun\n1\n:!\n:save\n:save {path}\n"));
+
+        let transcript = fs::read_to_string(path).unwrap();
+        fs::remove_file(path).unwrap();
+        let expected = concat!(
+            ">> :rerun\n",
+            "no previous input to rerun\n",
+            ">> 1\n",
+            "Int(1) \n",
+            ">> :!\n",
+            "Int(1) \n",
+            ">> :save\n",
+            "usage: :save <path>\n",
+            ">> :save ",
+        );
+        assert_eq!(format!("{expected}{path}\n"), transcript);
+    }
+
+    #[test]
+    fn save_transcript_unwritable() {
+        let output = session(":save /nonexistent/transcript.txt\n");
+        assert!(output.contains("could not save transcript to /nonexistent/transcript.txt"));
+    }
+
+    #[test]
+    fn commands_match_whole_line() {
+        let output = session("exit now\n:clear x\n:save\t/nonexistent/transcript.txt\n");
+        let expected = concat!(
+            ">> Ident(\"exit\") Ident(\"now\") \n",
+            ">> Colon Ident(\"clear\") Ident(\"x\") \n",
+        );
+        assert!(output.starts_with(expected), "{output}");
+        assert!(output.contains("could not save transcript to /nonexistent/transcript.txt"));
+    }
+
+    #[test]
+    fn paste_mode() {
+        let output = session(":paste\nlet a = 1;\nlet b = a;\n.\nexit\n");
+        let expected = concat!(
+            ">> ",
+            "Let Ident(\"a\") Assign Int(1) Semicolon ",
+            "Let Ident(\"b\") Assign Ident(\"a\") Semicolon \n",
+            ">> ",
+        );
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn paste_mode_eof() {
+        let output = session(":paste\nlet a = 1;\n");
+        assert_eq!(">> Let Ident(\"a\") Assign Int(1) Semicolon \n>> ", output);
     }
 }
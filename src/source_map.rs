@@ -0,0 +1,51 @@
+pub struct SourceMap {
+    source: String,
+    line_starts: Vec<usize>,
+}
+
+impl SourceMap {
+    pub fn new(source: String) -> SourceMap {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        SourceMap {
+            source,
+            line_starts,
+        }
+    }
+
+    /// Maps a byte offset to a 1-based `(line, column)`, with columns counted in chars.
+    pub fn byte_to_line_col(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.source.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let start = self.line_starts[line];
+        let col = self.source[start..]
+            .char_indices()
+            .take_while(|(i, _)| start + i < offset)
+            .count();
+        (line + 1, col + 1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SourceMap;
+
+    #[test]
+    fn byte_to_line_col() {
+        let source = String::from("let a = 1;\nlet é = \"→\";\n\nx");
+        let map = SourceMap::new(source);
+
+        assert_eq!((1, 1), map.byte_to_line_col(0));
+        assert_eq!((1, 5), map.byte_to_line_col(4));
+        assert_eq!((1, 11), map.byte_to_line_col(10));
+        assert_eq!((2, 1), map.byte_to_line_col(11));
+        assert_eq!((2, 5), map.byte_to_line_col(15));
+        assert_eq!((2, 7), map.byte_to_line_col(18));
+        assert_eq!((2, 10), map.byte_to_line_col(21));
+        assert_eq!((2, 11), map.byte_to_line_col(24));
+        assert_eq!((3, 1), map.byte_to_line_col(27));
+        assert_eq!((4, 1), map.byte_to_line_col(28));
+        assert_eq!((4, 2), map.byte_to_line_col(100));
+    }
+}